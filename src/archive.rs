@@ -0,0 +1,107 @@
+extern crate chrono;
+extern crate flate2;
+extern crate tar;
+extern crate zip;
+extern crate zstd;
+
+use std::io::Read;
+use std::path::{Path,PathBuf};
+use std::time::{Duration,SystemTime};
+
+use crate::FileInfo;
+
+// Whether `path`'s name looks like a supported archive, by extension.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".zip") || name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tar.zst")
+}
+
+// Enumerates the image members of the archive at `path`, applying `matches` to each
+// member's synthetic path, leading bytes, and size. Synthetic paths look like
+// `archive.tar::member/path.png`. Archives this can't open are silently skipped, same
+// as a directory this tool can't read.
+pub fn list_members(path: &Path, matches: impl Fn(&Path, &[u8], u64) -> bool) -> Vec<(FileInfo, PathBuf)> {
+    let name = path.to_string_lossy();
+    let members = if name.ends_with(".zip") {
+        list_zip_members(path, &matches)
+    } else if name.ends_with(".tar.gz") {
+        list_tar_gz_members(path, &matches)
+    } else if name.ends_with(".tar.zst") {
+        list_tar_zst_members(path, &matches)
+    } else {
+        list_tar_members(path, &matches)
+    };
+    members.unwrap_or_default()
+}
+
+fn synthetic_path(archive_path: &Path, member: &str) -> PathBuf {
+    PathBuf::from(format!("{}::{}", archive_path.to_string_lossy(), member))
+}
+
+fn list_zip_members(path: &Path, matches: &impl Fn(&Path, &[u8], u64) -> bool) -> Option<Vec<(FileInfo, PathBuf)>> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut zip = zip::ZipArchive::new(file).ok()?;
+    let mut out = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).ok()?;
+        if entry.is_dir() {
+            continue
+        }
+        let member_path = synthetic_path(path, entry.name());
+        let size = entry.size();
+
+        let mut header = [0u8; 16];
+        let n = entry.read(&mut header).unwrap_or(0);
+        if matches(&member_path, &header[..n], size) {
+            let modified = zip_datetime_to_systemtime(entry.last_modified());
+            out.push((FileInfo { modified, len: size }, member_path));
+        }
+    }
+    Some(out)
+}
+
+fn zip_datetime_to_systemtime(datetime: zip::DateTime) -> SystemTime {
+    use chrono::{NaiveDate,TimeZone,Utc};
+    NaiveDate::from_ymd_opt(datetime.year() as i32, datetime.month() as u32, datetime.day() as u32)
+        .and_then(|date| date.and_hms_opt(datetime.hour() as u32, datetime.minute() as u32, datetime.second() as u32) )
+        .map(|naive| Utc.from_utc_datetime(&naive) )
+        .map(|utc| SystemTime::UNIX_EPOCH + Duration::from_secs(utc.timestamp().max(0) as u64) )
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn list_tar_members(path: &Path, matches: &impl Fn(&Path, &[u8], u64) -> bool) -> Option<Vec<(FileInfo, PathBuf)>> {
+    list_tar_from_reader(path, std::fs::File::open(path).ok()?, matches)
+}
+
+fn list_tar_gz_members(path: &Path, matches: &impl Fn(&Path, &[u8], u64) -> bool) -> Option<Vec<(FileInfo, PathBuf)>> {
+    let file = std::fs::File::open(path).ok()?;
+    list_tar_from_reader(path, flate2::read::GzDecoder::new(file), matches)
+}
+
+fn list_tar_zst_members(path: &Path, matches: &impl Fn(&Path, &[u8], u64) -> bool) -> Option<Vec<(FileInfo, PathBuf)>> {
+    let file = std::fs::File::open(path).ok()?;
+    let decoder = zstd::stream::read::Decoder::new(file).ok()?;
+    list_tar_from_reader(path, decoder, matches)
+}
+
+fn list_tar_from_reader<R: Read>(archive_path: &Path, reader: R, matches: &impl Fn(&Path, &[u8], u64) -> bool) -> Option<Vec<(FileInfo, PathBuf)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut out = Vec::new();
+    for entry in archive.entries().ok()? {
+        let mut entry = entry.ok()?;
+        if entry.header().entry_type().is_dir() {
+            continue
+        }
+        let member_name = entry.path().ok()?.to_string_lossy().into_owned();
+        let member_path = synthetic_path(archive_path, &member_name);
+        let len = entry.size();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(entry.header().mtime().unwrap_or(0));
+
+        let mut header = [0u8; 16];
+        let n = entry.read(&mut header).unwrap_or(0);
+        if matches(&member_path, &header[..n], len) {
+            out.push((FileInfo { modified, len }, member_path));
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,34 @@
+extern crate base64;
+extern crate sha2;
+
+use base64::Engine;
+use sha2::{Sha256,Digest};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::Path;
+
+pub struct DataUrlEncoder {
+    seen: HashMap<[u8; 32], usize>,
+}
+
+impl DataUrlEncoder {
+    pub fn new() -> Self {
+        Self { seen: HashMap::new() }
+    }
+
+    // Encodes `path` as a `data:<mime>;base64,...` URL, or, if a payload with the same
+    // SHA-256 was already emitted, returns a short line pointing back at its index.
+    pub fn encode(&mut self, path: &Path, mime: &str) -> std::io::Result<String> {
+        let payload = base64::engine::general_purpose::STANDARD.encode(std::fs::read(path)?);
+        let digest: [u8; 32] = Sha256::digest(payload.as_bytes()).into();
+
+        let next_index = self.seen.len();
+        match self.seen.entry(digest) {
+            Entry::Occupied(entry) => Ok(format!("# duplicate of index {}", entry.get())),
+            Entry::Vacant(entry) => {
+                entry.insert(next_index);
+                Ok(format!("data:{};base64,{}", mime, payload))
+            }
+        }
+    }
+}
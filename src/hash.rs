@@ -0,0 +1,40 @@
+extern crate image;
+
+use std::path::Path;
+
+// Decodes `path`, converts to grayscale, resizes to 9x8, then for each of the 8 rows
+// compares each pixel to its right neighbor, emitting a 1 bit if left > right.
+pub fn dhash(path: &Path) -> Option<u64> {
+    let image = image::open(path).ok()?
+        .grayscale()
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash: u64 = 0;
+    for row in 0..8 {
+        for col in 0..8 {
+            let left = image.get_pixel(col, row)[0];
+            let right = image.get_pixel(col + 1, row)[0];
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+    Some(hash)
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// Groups `(index, hash)` pairs whose dHash is within `threshold` Hamming distance of a
+// group's first member, by pairwise comparison against each group's representative.
+// Fine for the file counts this tool typically scans; a BK-tree would pay off at scale.
+pub fn group_by_similarity(hashes: &[(usize, u64)], threshold: u32) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(u64, Vec<usize>)> = Vec::new();
+    for &(index, hash) in hashes {
+        match groups.iter_mut().find(|(representative, _)| hamming_distance(hash, *representative) <= threshold) {
+            Some((_, members)) => members.push(index),
+            None => groups.push((hash, vec![index])),
+        }
+    }
+    groups.into_iter().map(|(_,members)| members).collect()
+}
@@ -1,10 +1,21 @@
 extern crate chrono;
+extern crate glob;
 extern crate shlex;
 extern crate structopt;
 use crate::structopt::StructOpt;
-use std::collections::HashSet;
+#[cfg(feature = "duplicates")]
+mod hash;
+#[cfg(feature = "data-url")]
+mod dataurl;
+#[cfg(feature = "archives")]
+mod archive;
+use std::collections::{HashSet,VecDeque};
 use std::io::Write;
 use std::path::{Path,PathBuf};
+use std::sync::{Arc,Condvar,Mutex};
+use std::sync::mpsc;
+use std::thread;
+use std::time::SystemTime;
 
 
 
@@ -15,6 +26,10 @@ struct CLIArguments {
                 help="Enable processing of hidden subfiles/directories of targets")]
     dohidden: bool,
 
+    #[structopt(short="j", long="threads", value_name="N",
+                help="Number of worker threads to use (default: number of logical CPUs)")]
+    threads: Option<usize>,
+
     #[structopt(short="n", long,
                 help="Disable sorting by last modified time")]
     no_sort: bool,
@@ -31,6 +46,59 @@ struct CLIArguments {
                 help="File extensions to filter for (default: dpx exr gif heic jpeg jpg png svg tiff webp)")]
     extensions: Vec<String>,
 
+    #[structopt(long, value_name="PRESET", number_of_values=1,
+                help="Merge a named extension preset into the filter set (repeatable): raw, web, hdr")]
+    preset: Vec<String>,
+
+    #[structopt(long, value_name="GLOB", number_of_values=1,
+                help="Exclude paths whose normalized form matches this glob (repeatable)")]
+    exclude: Vec<String>,
+
+    #[structopt(short, long,
+                help="Detect images by content (magic bytes) rather than trusting the file extension\n  SVG/EXR/DPX aren't sniffable and still fall back to the extension set")]
+    content: bool,
+
+    #[structopt(long,
+                help="Group visually identical/near-identical images by perceptual hash (dHash)\n  Requires the `duplicates` feature")]
+    duplicates: bool,
+
+    #[cfg_attr(not(feature = "duplicates"), allow(dead_code))]
+    #[structopt(long, default_value="10", value_name="N",
+                help="Hamming-distance threshold for --duplicates (0 = exact match)")]
+    threshold: u32,
+
+    #[structopt(long, value_name="PX",
+                help="Minimum image width in pixels")]
+    min_width: Option<u32>,
+
+    #[structopt(long, value_name="PX",
+                help="Maximum image width in pixels")]
+    max_width: Option<u32>,
+
+    #[structopt(long, value_name="PX",
+                help="Minimum image height in pixels")]
+    min_height: Option<u32>,
+
+    #[structopt(long, value_name="PX",
+                help="Maximum image height in pixels")]
+    max_height: Option<u32>,
+
+    #[structopt(long, value_name="BYTES",
+                help="Minimum file size in bytes")]
+    min_size: Option<u64>,
+
+    #[structopt(long, value_name="BYTES",
+                help="Maximum file size in bytes")]
+    max_size: Option<u64>,
+
+    #[structopt(long="data-url",
+                help="Emit base64 data URLs instead of paths, deduped by SHA-256 of the payload\n  Requires the `data-url` feature")]
+    data_url: bool,
+
+    #[structopt(long,
+                help="Also look for images inside .zip/.tar/.tar.gz/.tar.zst archives\n  Requires the `archives` feature")]
+    archives: bool,
+
     #[structopt(value_name="TARGET",
                 help="Target files and directories (recursive)\n  If none specified, current working directory is implied")]
     targets: Vec<String>,
@@ -47,22 +115,88 @@ fn main() -> Result<(), Box<dyn std::error::Error>>{
         args
     };
 
-    let valid_extensions: HashSet<&str> = if args.extensions.is_empty() {
+    if args.archives {
+        #[cfg(not(feature = "archives"))]
+        {
+            eprintln!("find-images was built without the `archives` feature; rebuild with `--features archives` to use --archives");
+            std::process::exit(1);
+        }
+    }
+
+    let mut valid_extensions: HashSet<&str> = if args.extensions.is_empty() && args.preset.is_empty() {
         ["dpx", "exr", "gif", "heic", "jpeg", "jpg", "png", "svg", "tiff", "webp"].into_iter().collect()
     } else {
         args.extensions.iter().map(|s| s.as_str()).collect()
     };
+    for preset in &args.preset {
+        match preset_extensions(preset) {
+            Some(extensions) => valid_extensions.extend(extensions.iter().copied()),
+            None => eprintln!("Unknown --preset {:?}, ignoring", preset),
+        }
+    }
 
-    let mut registry = Registry::new(valid_extensions);
+    let excludes: Vec<glob::Pattern> = args.exclude.iter().filter_map(|pattern| {
+        match glob::Pattern::new(pattern) {
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                eprintln!("Invalid --exclude pattern {:?}: {}", pattern, e);
+                None
+            }
+        }
+    }).collect();
+
+    let filters = ScanFilters {
+        valid_extensions,
+        content: args.content,
+        excludes,
+        min_width: args.min_width,
+        max_width: args.max_width,
+        min_height: args.min_height,
+        max_height: args.max_height,
+        min_size: args.min_size,
+        max_size: args.max_size,
+    };
 
-    registry.populate(args.targets.into_iter().map(|target| Path::new(&target).to_path_buf() ), args.dohidden);
+    let mut registry = Registry::new(filters);
 
-    if !args.no_sort {
+    registry.populate(args.targets.into_iter().map(|target| Path::new(&target).to_path_buf() ), args.dohidden, args.archives, args.threads);
+
+    if args.no_sort {
+        registry.sort_by_path();
+    } else {
         registry.sort_by_modified();
     }
 
     let stdout = std::io::stdout();
     let mut stdout_buffer = std::io::BufWriter::new(stdout.lock());
+
+    if args.duplicates {
+        #[cfg(feature = "duplicates")]
+        {
+            let groups = registry.group_duplicates(args.threshold);
+            registry.write_groups(&mut stdout_buffer, &groups, args.null, args.quote)?;
+            return Ok(());
+        }
+        #[cfg(not(feature = "duplicates"))]
+        {
+            eprintln!("find-images was built without the `duplicates` feature; rebuild with `--features duplicates` to use --duplicates");
+            std::process::exit(1);
+        }
+    }
+
+    if args.data_url {
+        #[cfg(feature = "data-url")]
+        {
+            registry.write_data_urls(&mut stdout_buffer, args.null)?;
+            return Ok(());
+        }
+        #[cfg(not(feature = "data-url"))]
+        {
+            eprintln!("find-images was built without the `data-url` feature; rebuild with `--features data-url` to use --data-url");
+            std::process::exit(1);
+        }
+    }
+
     registry.write_all(&mut stdout_buffer, args.null, args.quote)?;
 
     Ok(())
@@ -70,13 +204,112 @@ fn main() -> Result<(), Box<dyn std::error::Error>>{
 
 
 
+// Named extension groups selectable via `--preset`, merged into `valid_extensions`.
+const PRESET_RAW: [&str; 17] = ["cr2", "cr3", "nef", "arw", "dng", "orf", "raf", "rw2", "pef", "srw", "mrw", "3fr", "iiq", "mos", "erf", "kdc", "dcr"];
+const PRESET_WEB: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "svg"];
+const PRESET_HDR: [&str; 3] = ["exr", "dpx", "hdr"];
+
+fn preset_extensions(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "raw" => Some(&PRESET_RAW),
+        "web" => Some(&PRESET_WEB),
+        "hdr" => Some(&PRESET_HDR),
+        _ => None
+    }
+}
+
+// The filters applied to every candidate file during a scan. Bundled together since
+// `populate`/`add_dir` thread them down to each worker as a single unit.
+struct ScanFilters<'a> {
+    valid_extensions: HashSet<&'a str>,
+    content: bool,
+    excludes: Vec<glob::Pattern>,
+    min_width: Option<u32>,
+    max_width: Option<u32>,
+    min_height: Option<u32>,
+    max_height: Option<u32>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+}
+impl<'a> ScanFilters<'a> {
+    fn wants_dimensions(&self) -> bool {
+        self.min_width.is_some() || self.max_width.is_some() || self.min_height.is_some() || self.max_height.is_some()
+    }
+}
+
+// Minimal per-entry metadata, decoupled from std::fs::Metadata so archive members
+// (which have no filesystem Metadata of their own) can be registered uniformly.
+pub struct FileInfo {
+    modified: SystemTime,
+    len: u64,
+}
+impl From<&std::fs::Metadata> for FileInfo {
+    fn from(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            len: metadata.len(),
+        }
+    }
+}
+
+// The shared pending-directory queue, plus a pending count covered by the same lock so
+// workers can block on the condvar instead of spinning while waiting for more work.
+struct WorkQueue {
+    state: Mutex<WorkQueueState>,
+    condvar: Condvar,
+}
+struct WorkQueueState {
+    dirs: VecDeque<PathBuf>,
+    pending: usize,
+}
+impl WorkQueue {
+    fn new() -> Self {
+        Self { state: Mutex::new(WorkQueueState { dirs: VecDeque::new(), pending: 0 }), condvar: Condvar::new() }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().pending == 0
+    }
+
+    fn push(&self, dir: PathBuf) {
+        let mut state = self.state.lock().unwrap();
+        state.pending += 1;
+        state.dirs.push_back(dir);
+        self.condvar.notify_one();
+    }
+
+    // Blocks until a directory is available, or returns None once no worker has any
+    // pending directories left, waking every other waiter so they can also exit.
+    fn pop(&self) -> Option<PathBuf> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(dir) = state.dirs.pop_front() {
+                return Some(dir)
+            }
+            if state.pending == 0 {
+                self.condvar.notify_all();
+                return None
+            }
+            state = self.condvar.wait(state).unwrap();
+        }
+    }
+
+    fn finish_one(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.pending -= 1;
+        if state.pending == 0 {
+            self.condvar.notify_all();
+        }
+    }
+}
+
 struct Registry<'a> {
-    registry: Vec<(std::fs::Metadata, PathBuf)>,
-    valid_extensions: HashSet<&'a str>
+    registry: Vec<(FileInfo, PathBuf)>,
+    filters: ScanFilters<'a>
 }
 impl<'a> Registry<'a> {
-    pub fn new(valid_extensions: HashSet<&'a str>) -> Self {
-        Self { registry: Vec::new(), valid_extensions }
+    pub fn new(filters: ScanFilters<'a>) -> Self {
+        Self { registry: Vec::new(), filters }
     }
 
     pub fn write_all(&self, writer: &mut impl Write, separator_null: bool, quote: bool) -> std::io::Result<()> {
@@ -91,55 +324,403 @@ impl<'a> Registry<'a> {
     }
 
     pub fn sort_by_modified(&mut self) {
-        self.registry.sort_by_key(|(meta,_)| {
-            meta.modified().ok().unwrap_or_else(
-                || std::time::SystemTime::UNIX_EPOCH,
-            )
-        });
+        self.registry.sort_by(|(info_a,path_a), (info_b,path_b)| info_a.modified.cmp(&info_b.modified).then_with(|| path_a.cmp(path_b)) );
     }
 
-    pub fn populate(&mut self, source_paths: impl Iterator<Item=PathBuf>, dohidden: bool) {
+    pub fn populate(&mut self, source_paths: impl Iterator<Item=PathBuf>, dohidden: bool, archives: bool, threads: Option<usize>) {
+        let queue = Arc::new(WorkQueue::new());
+
         for path in source_paths {
             if path.is_file() {
                 if let Ok(metadata) = std::fs::metadata(&path) { // intentionally not symlink_metadata
-                    self.add_file(path, metadata);
+                    self.add_file(path, FileInfo::from(&metadata));
                 }
             } else if path.is_dir() {
-                self.add_dir(path, dohidden);
+                if is_excluded(&path, &self.filters.excludes) {
+                    continue
+                }
+                queue.push(path);
+            }
+        }
+
+        if queue.is_empty() {
+            return
+        }
+
+        let num_workers = threads.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1) ).max(1);
+        let (tx, rx) = mpsc::channel::<(FileInfo, PathBuf)>();
+        let filters = &self.filters;
+
+        thread::scope(|scope| {
+            for _ in 0..num_workers {
+                let queue = Arc::clone(&queue);
+                let tx = tx.clone();
+                scope.spawn(move || while let Some(dir) = queue.pop() {
+                    visit_dir(&dir, dohidden, archives, filters, &queue, &tx);
+                    queue.finish_one();
+                });
             }
+            drop(tx);
+            for (info, path) in rx {
+                self.registry.push((info, path));
+            }
+        });
+    }
+
+    // Workers race to send results back during populate(), so without this, the
+    // registry's order (and anything stabilized against it, like a modified-time sort
+    // with ties) is nondeterministic from run to run. Callers that don't need
+    // `sort_by_modified`'s ordering should call this instead so --no-sort output stays
+    // reproducible regardless of thread scheduling.
+    pub fn sort_by_path(&mut self) {
+        self.registry.sort_by(|(_,path_a), (_,path_b)| path_a.cmp(path_b));
+    }
+
+    fn add_file(&mut self, path: PathBuf, info: FileInfo) {
+        if matches_filters(&path, info.len, &self.filters) {
+            self.registry.push((info, path));
+        }
+    }
+
+    // Groups registry entries by dHash similarity, each group sorted oldest-first.
+    #[cfg(feature = "duplicates")]
+    pub fn group_duplicates(&self, threshold: u32) -> Vec<Vec<usize>> {
+        let hashes: Vec<(usize, u64)> = self.registry.iter().enumerate()
+            .filter_map(|(i, (_,path))| hash::dhash(path).map(|h| (i, h)) )
+            .collect();
+
+        let mut groups = hash::group_by_similarity(&hashes, threshold);
+        for group in &mut groups {
+            group.sort_by(|&a, &b| self.registry[a].0.modified.cmp(&self.registry[b].0.modified).then_with(|| self.registry[a].1.cmp(&self.registry[b].1)) );
         }
+        groups
     }
 
-    fn add_file(&mut self, path: PathBuf, metadata: std::fs::Metadata) {
-        if let Some(osstr_ext) = path.extension() {
-            match osstr_ext.to_str() {
-                Some(ext) => if self.valid_extensions.contains(ext) {
-                    self.registry.push((metadata, path));
-                },
-                None => eprintln!(
+    #[cfg(feature = "duplicates")]
+    pub fn write_groups(&self, writer: &mut impl Write, groups: &[Vec<usize>], separator_null: bool, quote: bool) -> std::io::Result<()> {
+        for (group_index, group) in groups.iter().enumerate() {
+            if group_index > 0 {
+                if separator_null { write!(writer, "\0")?; } else { writeln!(writer)?; }
+            }
+            if separator_null {
+                if quote { for &i in group { let file = &self.registry[i].1; write!(writer, "{}\0", shlex::try_quote(&file.to_string_lossy()).unwrap())?; } }
+                else     { for &i in group { let file = &self.registry[i].1; write!(writer, "{}\0",                  &file.to_string_lossy()          )?; } }
+            } else {
+                if quote { for &i in group { let file = &self.registry[i].1; writeln!(writer, "{}", shlex::try_quote(&file.to_string_lossy()).unwrap())?; } }
+                else     { for &i in group { let file = &self.registry[i].1; writeln!(writer, "{}",                  &file.to_string_lossy()          )?; } }
+            }
+        }
+        Ok(())
+    }
+
+    // Emits each file as a `data:<mime>;base64,...` line, deduped by SHA-256 of the
+    // payload; repeats are printed as a short index line instead of re-emitting the blob.
+    #[cfg(feature = "data-url")]
+    pub fn write_data_urls(&self, writer: &mut impl Write, separator_null: bool) -> std::io::Result<()> {
+        let mut encoder = dataurl::DataUrlEncoder::new();
+        for (_,path) in &self.registry {
+            let line = match encoder.encode(path, detect_mime(path)) {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("Cannot read {} for --data-url: {}", shlex::try_quote(&path.to_string_lossy()).unwrap(), e);
+                    continue
+                }
+            };
+            if separator_null { write!(writer, "{}\0", line)?; } else { writeln!(writer, "{}", line)?; }
+        }
+        Ok(())
+    }
+}
+
+// Extensions whose content isn't cheaply sniffable by magic bytes; `--content` still
+// trusts the extension for these.
+const UNSNIFFABLE_EXTENSIONS: [&str; 3] = ["svg", "exr", "dpx"];
+
+fn file_extension_matches(path: &Path, valid_extensions: &HashSet<&str>) -> bool {
+    match path.extension() {
+        Some(osstr_ext) => match osstr_ext.to_str() {
+            Some(ext) => valid_extensions.contains(ext),
+            None => {
+                eprintln!(
                     "Cannot read non-utf-8 file extension: {} on {}",
                     shlex::try_quote(&osstr_ext.to_string_lossy()).unwrap(),
                     shlex::try_quote(&path.to_string_lossy()).unwrap()
-                )
+                );
+                false
             }
+        },
+        None => false
+    }
+}
+
+// Reads the first ~16 bytes of `path` and matches them against known image signatures.
+fn sniff_magic_bytes(path: &Path) -> Option<&'static str> {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    let n = std::fs::File::open(path).ok()?.read(&mut header).ok()?;
+    sniff_magic_bytes_from_header(&header[..n])
+}
+
+// Matches a pre-read header (as from an archive member) against known image signatures.
+fn sniff_magic_bytes_from_header(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) { return Some("png") }
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) { return Some("jpeg") }
+    if header.starts_with(&[0x47, 0x49, 0x46, 0x38]) { return Some("gif") }
+    if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WEBP" { return Some("webp") }
+    if header.starts_with(&[0x49, 0x49, 0x2A, 0x00]) || header.starts_with(&[0x4D, 0x4D, 0x00, 0x2A]) { return Some("tiff") }
+    if header.len() >= 12 && &header[4..8] == b"ftyp" {
+        let brand = &header[8..12];
+        if brand == b"heic" || brand == b"heix" || brand == b"mif1" {
+            return Some("heic")
         }
     }
+    None
+}
+
+// Determines whether `path` should be registered, either by trusted extension or,
+// under `--content`, by sniffing its magic bytes (falling back to the extension for
+// the formats in `UNSNIFFABLE_EXTENSIONS`, which have no cheap magic-byte signature).
+fn is_wanted_image(path: &Path, valid_extensions: &HashSet<&str>, content: bool) -> bool {
+    if !content {
+        return file_extension_matches(path, valid_extensions)
+    }
+    if sniff_magic_bytes(path).is_some() {
+        return true
+    }
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => UNSNIFFABLE_EXTENSIONS.contains(&ext) && valid_extensions.contains(ext),
+        None => false
+    }
+}
+
+// Like `is_wanted_image`, but for an archive member whose header bytes were already
+// read from the archive stream rather than a real file on disk.
+#[cfg(feature = "archives")]
+fn is_wanted_member(member_path: &Path, header: &[u8], valid_extensions: &HashSet<&str>, content: bool) -> bool {
+    if !content {
+        return file_extension_matches(member_path, valid_extensions)
+    }
+    if sniff_magic_bytes_from_header(header).is_some() {
+        return true
+    }
+    match member_path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => UNSNIFFABLE_EXTENSIONS.contains(&ext) && valid_extensions.contains(ext),
+        None => false
+    }
+}
+
+// Like `matches_filters`, but for an archive member: applies the --exclude check, the
+// extension/content check, and the size filter against a header and size read from the
+// archive stream. Dimension filters aren't applied to members, since that would require
+// decoding each member's full pixel data rather than a cheap header read.
+#[cfg(feature = "archives")]
+fn matches_member_filters(member_path: &Path, header: &[u8], size: u64, filters: &ScanFilters) -> bool {
+    if is_excluded(member_path, &filters.excludes) {
+        return false
+    }
+
+    if !is_wanted_member(member_path, header, &filters.valid_extensions, filters.content) {
+        return false
+    }
+
+    if filters.min_size.is_some_and(|min| size < min) { return false }
+    if filters.max_size.is_some_and(|max| size > max) { return false }
+
+    true
+}
+
+// Infers a MIME type for `path`, preferring the sniffed magic-byte type over the
+// declared extension since the former is more likely to be accurate.
+#[cfg(feature = "data-url")]
+fn detect_mime(path: &Path) -> &'static str {
+    let sniffed_or_extension = sniff_magic_bytes(path).or_else(|| path.extension().and_then(|ext| ext.to_str()) );
+    match sniffed_or_extension {
+        Some("png") => "image/png",
+        Some("jpeg") | Some("jpg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("tiff") => "image/tiff",
+        Some("heic") => "image/heic",
+        Some("svg") => "image/svg+xml",
+        Some("exr") => "image/x-exr",
+        Some("dpx") => "image/x-dpx",
+        _ => "application/octet-stream"
+    }
+}
+
+// Reads just enough of `path`'s header to parse its pixel dimensions, without decoding
+// any pixels. Returns None for formats this doesn't know how to parse the header of.
+fn read_dimensions(path: &Path) -> Option<(u32, u32)> {
+    use std::io::Read;
+    let mut buf = Vec::with_capacity(65536);
+    std::fs::File::open(path).ok()?.take(65536).read_to_end(&mut buf).ok()?;
+
+    read_png_dimensions(&buf)
+        .or_else(|| read_gif_dimensions(&buf) )
+        .or_else(|| read_webp_dimensions(&buf) )
+        .or_else(|| read_jpeg_dimensions(&buf) )
+}
 
-    fn add_dir(&mut self, path: PathBuf, dohidden: bool) {
-        if let Ok(entries) = std::fs::read_dir(path) {
-            for path in entries.filter_map(|e| e.ok() ).map(|e| e.path() ) {
-                if !dohidden && path.file_name().map(|name| name.to_string_lossy().starts_with('.')).unwrap_or(true) { // this unwraps to None if the file_name is .. or is root / (neither of which would happen in this scenario)
+fn read_png_dimensions(buf: &[u8]) -> Option<(u32, u32)> {
+    if buf.len() < 24 || !buf.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return None
+    }
+    let width = u32::from_be_bytes(buf[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(buf[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+fn read_gif_dimensions(buf: &[u8]) -> Option<(u32, u32)> {
+    if buf.len() < 10 || !(buf.starts_with(b"GIF87a") || buf.starts_with(b"GIF89a")) {
+        return None
+    }
+    let width = u16::from_le_bytes(buf[6..8].try_into().ok()?) as u32;
+    let height = u16::from_le_bytes(buf[8..10].try_into().ok()?) as u32;
+    Some((width, height))
+}
+
+// Parses the VP8 (lossy), VP8L (lossless), or VP8X (extended) chunk that follows the
+// 12-byte RIFF/WEBP header; each encodes its dimensions differently.
+fn read_webp_dimensions(buf: &[u8]) -> Option<(u32, u32)> {
+    if buf.len() < 20 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"WEBP" {
+        return None
+    }
+    match &buf[12..16] {
+        b"VP8 " if buf.len() >= 30 => {
+            let width = u16::from_le_bytes(buf[26..28].try_into().ok()?) as u32 & 0x3FFF;
+            let height = u16::from_le_bytes(buf[28..30].try_into().ok()?) as u32 & 0x3FFF;
+            Some((width, height))
+        }
+        b"VP8L" if buf.len() >= 25 => {
+            let bits = u32::from_le_bytes(buf[21..25].try_into().ok()?);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            Some((width, height))
+        }
+        b"VP8X" if buf.len() >= 30 => {
+            let width = u32::from_le_bytes([buf[24], buf[25], buf[26], 0]) + 1;
+            let height = u32::from_le_bytes([buf[27], buf[28], buf[29], 0]) + 1;
+            Some((width, height))
+        }
+        _ => None
+    }
+}
+
+// Scans JPEG segments for an SOF0/SOF2 marker and reads the width/height that follow
+// its length and precision bytes.
+fn read_jpeg_dimensions(buf: &[u8]) -> Option<(u32, u32)> {
+    if !buf.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return None
+    }
+    let mut i = 2;
+    while i + 1 < buf.len() {
+        if buf[i] != 0xFF {
+            i += 1;
+            continue
+        }
+        let marker = buf[i+1];
+        if marker == 0x00 || marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            i += 2;
+            continue
+        }
+        if i + 9 > buf.len() {
+            break
+        }
+        let length = u16::from_be_bytes(buf[i+2..i+4].try_into().ok()?) as usize;
+        if marker == 0xC0 || marker == 0xC2 {
+            let height = u16::from_be_bytes(buf[i+5..i+7].try_into().ok()?) as u32;
+            let width = u16::from_be_bytes(buf[i+7..i+9].try_into().ok()?) as u32;
+            return Some((width, height))
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+// Whether `path`'s normalized form matches one of the `--exclude` glob patterns.
+fn is_excluded(path: &Path, excludes: &[glob::Pattern]) -> bool {
+    let normalized = path.to_string_lossy();
+    excludes.iter().any(|pattern| pattern.matches(&normalized) )
+}
+
+// Applies the --exclude check, the extension/content check, and any active
+// size/dimension filters.
+fn matches_filters(path: &Path, size: u64, filters: &ScanFilters) -> bool {
+    if is_excluded(path, &filters.excludes) {
+        return false
+    }
+
+    if !is_wanted_image(path, &filters.valid_extensions, filters.content) {
+        return false
+    }
+
+    if filters.min_size.is_some_and(|min| size < min) { return false }
+    if filters.max_size.is_some_and(|max| size > max) { return false }
+
+    if filters.wants_dimensions() {
+        match read_dimensions(path) {
+            Some((width, height)) => {
+                if filters.min_width.is_some_and(|min| width < min) { return false }
+                if filters.max_width.is_some_and(|max| width > max) { return false }
+                if filters.min_height.is_some_and(|min| height < min) { return false }
+                if filters.max_height.is_some_and(|max| height > max) { return false }
+            }
+            None => {
+                eprintln!(
+                    "Cannot read dimensions of {}, skipping due to an active dimension filter",
+                    shlex::try_quote(&path.to_string_lossy()).unwrap()
+                );
+                return false
+            }
+        }
+    }
+
+    true
+}
+
+// One unit of work for a traversal worker: scan a single directory (non-recursively),
+// pushing subdirectories back onto the shared queue and sending matched files over `tx`.
+fn visit_dir(
+    path: &Path,
+    dohidden: bool,
+    archives: bool,
+    filters: &ScanFilters,
+    queue: &Arc<WorkQueue>,
+    tx: &mpsc::Sender<(FileInfo, PathBuf)>,
+) {
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for path in entries.filter_map(|e| e.ok() ).map(|e| e.path() ) {
+            if !dohidden && path.file_name().map(|name| name.to_string_lossy().starts_with('.')).unwrap_or(true) { // this unwraps to None if the file_name is .. or is root / (neither of which would happen in this scenario)
+                continue
+            }
+            if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+                if metadata.file_type().is_symlink() {
                     continue
                 }
-                if let Ok(metadata) = std::fs::symlink_metadata(&path) {
-                    if metadata.file_type().is_symlink() {
-                        continue
+                if path.is_file() {
+                    if archives {
+                        #[cfg(feature = "archives")]
+                        if archive::is_archive(&path) {
+                            if is_excluded(&path, &filters.excludes) {
+                                continue
+                            }
+                            for (info, member_path) in archive::list_members(&path, |member_path, header, size| {
+                                matches_member_filters(member_path, header, size, filters)
+                            }) {
+                                let _ = tx.send((info, member_path));
+                            }
+                            continue
+                        }
                     }
-                    if path.is_file() {
-                        self.add_file(path, metadata)
-                    } else if path.is_dir() {
-                        self.add_dir(path, dohidden);
+
+                    if matches_filters(&path, metadata.len(), filters) {
+                        let _ = tx.send((FileInfo::from(&metadata), path));
+                    }
+                } else if path.is_dir() {
+                    if is_excluded(&path, &filters.excludes) {
+                        continue
                     }
+                    queue.push(path);
                 }
             }
         }